@@ -1,6 +1,6 @@
 use regex::Regex;
 use clap::{Parser, Subcommand};
-use sazan::{crop_and_grid_images, crop_split_image_to_zip};
+use sazan::{crop_and_grid_images, crop_split_image_to_zip, encode_image, optimize_png, supported_extensions, OutputFormat, ResizeFilter, ResizeSpec, ZipCompression};
 use std::path::Path;
 
 #[derive(Parser, Debug)]
@@ -18,9 +18,9 @@ enum Commands {
         #[arg(required = true)]
         images: Vec<String>,
 
-        /// Output file name (default: result.png)
-        #[arg(short, long, default_value = "result.png")]
-        output: String,
+        /// Output file name (default: derived from --format, e.g. result.png)
+        #[arg(short, long)]
+        output: Option<String>,
 
         /// Crop parameter in the format WIDTHxHEIGHT+X+Y (e.g. 1265x1265+1422+366).
         /// WIDTH and HEIGHT specify the crop size, X and Y specify the top-left offset in the source image.
@@ -30,6 +30,30 @@ enum Commands {
         /// Grid size (e.g. 3x3, 4x2)
         #[arg(short, long, required = true, value_parser = parse_grid_param_clap)]
         grid: (usize, usize),
+
+        /// Output encoder: png, jpeg[:QUALITY], webp[:QUALITY] or tiff (default: inferred from --output extension)
+        #[arg(short, long, value_parser = parse_format_param_clap)]
+        format: Option<OutputFormat>,
+
+        /// Number of rayon worker threads to use for cropping (0 = rayon default)
+        #[arg(short, long, default_value_t = 0)]
+        threads: usize,
+
+        /// Lossless PNG optimization level 0-6 (0 = skip, default); ignored for non-PNG output
+        #[arg(long, default_value_t = 0)]
+        optimize: u8,
+
+        /// Resize each cropped region to WIDTHxHEIGHT before gridding (mutually exclusive with --scale)
+        #[arg(long, value_parser = parse_resize_param_clap)]
+        resize: Option<(u32, u32)>,
+
+        /// Scale each cropped region by a factor before gridding (mutually exclusive with --resize)
+        #[arg(long)]
+        scale: Option<f32>,
+
+        /// Resampling filter used by --resize/--scale
+        #[arg(long, value_parser = parse_filter_param_clap, default_value = "lanczos3")]
+        filter: ResizeFilter,
     },
     /// Crop and split images into tiles and save as a ZIP archive
     CropSplit {
@@ -53,9 +77,80 @@ enum Commands {
         /// Filename prefix for tiles in the zip (default: tile)
         #[arg(long, default_value = "tile")]
         prefix: String,
+
+        /// Output encoder for tiles: png, jpeg[:QUALITY], webp[:QUALITY] or tiff (default: png)
+        #[arg(short, long, value_parser = parse_format_param_clap, default_value = "png")]
+        format: OutputFormat,
+
+        /// Number of rayon worker threads to use for cropping and encoding (0 = rayon default)
+        #[arg(short, long, default_value_t = 0)]
+        threads: usize,
+
+        /// Lossless PNG optimization level 0-6 (0 = skip, default); ignored for non-PNG output
+        #[arg(long, default_value_t = 0)]
+        optimize: u8,
+
+        /// Resize each cropped tile to WIDTHxHEIGHT before encoding (mutually exclusive with --scale)
+        #[arg(long, value_parser = parse_resize_param_clap)]
+        resize: Option<(u32, u32)>,
+
+        /// Scale each cropped tile by a factor before encoding (mutually exclusive with --resize)
+        #[arg(long)]
+        scale: Option<f32>,
+
+        /// Resampling filter used by --resize/--scale
+        #[arg(long, value_parser = parse_filter_param_clap, default_value = "lanczos3")]
+        filter: ResizeFilter,
+
+        /// Skip tiles unchanged from the previous image (mean per-channel abs diff, scaled 0-255, at or below this value); recorded in manifest.json
+        #[arg(long)]
+        diff_threshold: Option<f32>,
+
+        /// ZIP compression: stored (default) or deflate[:LEVEL]
+        #[arg(long, value_parser = parse_compression_param_clap, default_value = "stored")]
+        compression: ZipCompression,
     },
 }
 
+/// Resolves `--resize`/`--scale` CLI options into a `ResizeSpec` paired with the chosen filter.
+/// Exits the process if both are given, since they're mutually exclusive.
+fn resolve_resize_param(
+    resize: Option<(u32, u32)>,
+    scale: Option<f32>,
+    filter: ResizeFilter,
+) -> Option<(ResizeSpec, ResizeFilter)> {
+    match (resize, scale) {
+        (Some(_), Some(_)) => {
+            eprintln!("--resize and --scale are mutually exclusive");
+            std::process::exit(1);
+        }
+        (Some((w, h)), None) => Some((ResizeSpec::Absolute(w, h), filter)),
+        (None, Some(factor)) => Some((ResizeSpec::Scale(factor), filter)),
+        (None, None) => None,
+    }
+}
+
+/// Runs `work` on a rayon thread pool sized to `threads` (0 = rayon's default pool).
+///
+/// # Arguments
+/// * `threads` - Worker thread count, or 0 to use rayon's default
+/// * `work` - Closure to execute on the pool
+///
+/// # Behavior
+/// Exits the process if the thread pool fails to build.
+fn run_with_thread_pool<T: Send>(threads: usize, work: impl FnOnce() -> T + Send) -> T {
+    if threads == 0 {
+        return work();
+    }
+    match rayon::ThreadPoolBuilder::new().num_threads(threads).build() {
+        Ok(pool) => pool.install(work),
+        Err(e) => {
+            eprintln!("Failed to build thread pool with {} threads: {}", threads, e);
+            std::process::exit(1);
+        }
+    }
+}
+
 /// For clap value_parser: parses a string like "1265x1265+1422+366" into a tuple (width, height, x, y).
 ///
 /// # Arguments
@@ -94,11 +189,105 @@ fn parse_grid_param_clap(s: &str) -> Result<(usize, usize), String> {
     }).ok_or_else(|| format!("Invalid grid format: {}", s))
 }
 
+/// For clap value_parser: parses a string like "png", "jpeg:90" or "webp:75.5" into an `OutputFormat`.
+///
+/// # Arguments
+/// * `s` - A format parameter string, e.g., "jpeg:90"
+///
+/// # Returns
+/// * `Ok(OutputFormat)` - The parsed output format on success
+/// * `Err(String)` - An error message if parsing fails
+fn parse_format_param_clap(s: &str) -> Result<OutputFormat, String> {
+    let re = Regex::new(r"^(?i)(png|jpe?g|webp|tiff?)(?::([0-9]+(?:\.[0-9]+)?))?$").unwrap();
+    let cap = re.captures(s).ok_or_else(|| format!("Invalid format: {}", s))?;
+    let codec = cap[1].to_ascii_lowercase();
+    let param = cap.get(2).map(|m| m.as_str());
+    match codec.as_str() {
+        "png" => Ok(OutputFormat::Png),
+        "jpg" | "jpeg" => {
+            let quality = param.map_or(Ok(85), |p| p.parse::<u8>().map_err(|e| e.to_string()))?;
+            Ok(OutputFormat::Jpeg { quality })
+        }
+        "webp" => {
+            let quality = param.map_or(Ok(80.0), |p| p.parse::<f32>().map_err(|e| e.to_string()))?;
+            if !(0.0..=100.0).contains(&quality) {
+                return Err(format!("WebP quality must be between 0 and 100, got {}", quality));
+            }
+            Ok(OutputFormat::WebP { quality })
+        }
+        "tif" | "tiff" => Ok(OutputFormat::Tiff),
+        _ => Err(format!("Invalid format: {}", s)),
+    }
+}
+
+/// For clap value_parser: parses a string like "800x600" into a tuple (width, height).
+///
+/// # Arguments
+/// * `s` - A resize parameter string, e.g., "800x600"
+///
+/// # Returns
+/// * `Ok((u32, u32))` - A tuple (width, height) on success
+/// * `Err(String)` - An error message if parsing fails
+fn parse_resize_param_clap(s: &str) -> Result<(u32, u32), String> {
+    let re = Regex::new(r"^(\d+)x(\d+)$").unwrap();
+    re.captures(s).and_then(|cap| {
+        Some((
+            cap[1].parse().ok()?,
+            cap[2].parse().ok()?,
+        ))
+    }).ok_or_else(|| format!("Invalid resize format: {}", s))
+}
+
+/// For clap value_parser: parses a resampling filter name into a `ResizeFilter`.
+///
+/// # Arguments
+/// * `s` - One of "nearest", "bilinear", "lanczos3"
+///
+/// # Returns
+/// * `Ok(ResizeFilter)` - The parsed filter on success
+/// * `Err(String)` - An error message if the name is unrecognized
+fn parse_filter_param_clap(s: &str) -> Result<ResizeFilter, String> {
+    match s.to_ascii_lowercase().as_str() {
+        "nearest" => Ok(ResizeFilter::Nearest),
+        "bilinear" => Ok(ResizeFilter::Bilinear),
+        "lanczos3" => Ok(ResizeFilter::Lanczos3),
+        _ => Err(format!("Invalid filter: {} (expected nearest, bilinear or lanczos3)", s)),
+    }
+}
+
+/// For clap value_parser: parses a string like "stored" or "deflate:6" into a `ZipCompression`.
+///
+/// # Arguments
+/// * `s` - One of "stored", "deflate" or "deflate:LEVEL"
+///
+/// # Returns
+/// * `Ok(ZipCompression)` - The parsed compression method on success
+/// * `Err(String)` - An error message if parsing fails
+fn parse_compression_param_clap(s: &str) -> Result<ZipCompression, String> {
+    let re = Regex::new(r"^(?i)(stored|deflate)(?::(\d+))?$").unwrap();
+    let cap = re.captures(s).ok_or_else(|| format!("Invalid compression: {}", s))?;
+    match cap[1].to_ascii_lowercase().as_str() {
+        "stored" => Ok(ZipCompression::Stored),
+        "deflate" => {
+            let level = cap.get(2).map(|m| m.as_str().parse::<i32>().map_err(|e| e.to_string())).transpose()?;
+            Ok(ZipCompression::Deflate { level })
+        }
+        _ => Err(format!("Invalid compression: {}", s)),
+    }
+}
+
+/// Determines the file extension of an output path, lowercased, without the dot.
+fn extension_of(path: &str) -> Option<String> {
+    Path::new(path)
+        .extension()
+        .map(|ext| ext.to_string_lossy().to_ascii_lowercase())
+}
+
 /// Loads images from file paths, crops them, arranges them in a grid, and saves the result to a file.
 ///
 /// # Arguments
 /// * `images` - List of image file paths (will be sorted)
-/// * `output` - Output file path for the combined image
+/// * `output` - Output file path for the combined image, or `None` to derive one from `format`
 /// * `crop` - Crop rectangle as (width, height, x, y)
 /// * `grid` - Grid size as (columns, rows)
 ///
@@ -106,8 +295,12 @@ fn parse_grid_param_clap(s: &str) -> Result<(usize, usize), String> {
 /// - Loads each image from the given paths (exits on error)
 /// - Crops each image to the specified rectangle
 /// - Arranges the cropped images in a grid (fills with transparency if not enough images)
+/// - Resolves the output encoder from `--format`, or from the `output` file extension if omitted
+///   (exits on error if neither yields a supported format, or if they disagree)
+/// - If `output` wasn't given, derives a filename from the resolved format's canonical extension
 /// - Saves the result to the specified output file (exits on error)
-fn run_crop_grid(images: Vec<String>, output: String, crop: (u32, u32, u32, u32), grid: (usize, usize)) {
+#[allow(clippy::too_many_arguments)]
+fn run_crop_grid(images: Vec<String>, output: Option<String>, crop: (u32, u32, u32, u32), grid: (usize, usize), format: Option<OutputFormat>, threads: usize, optimize: u8, resize: Option<(u32, u32)>, scale: Option<f32>, filter: ResizeFilter) {
     // Sort image file paths
     let mut images = images;
     images.sort();
@@ -124,16 +317,64 @@ fn run_crop_grid(images: Vec<String>, output: String, crop: (u32, u32, u32, u32)
         }
     }
 
+    // Resolve the output encoder from --format and/or the output file extension. `ext` is only
+    // `Some` when the user actually passed --output, so the mismatch check below never fires
+    // against a default we made up ourselves.
+    let ext = output.as_deref().and_then(extension_of);
+    let format = match (format, ext.as_deref()) {
+        (Some(format), Some(ext)) => {
+            if !format.extensions().contains(&ext) {
+                eprintln!(
+                    "Output extension '.{}' does not match --format (expected one of: {})",
+                    ext,
+                    format.extensions().join(", ")
+                );
+                std::process::exit(1);
+            }
+            format
+        }
+        (Some(format), None) => format,
+        (None, Some(ext)) => match OutputFormat::from_extension(ext) {
+            Some(format) => format,
+            None => {
+                eprintln!(
+                    "Cannot infer output format from extension '.{}'; pass --format (supported extensions: {})",
+                    ext,
+                    supported_extensions().join(", ")
+                );
+                std::process::exit(1);
+            }
+        },
+        (None, None) => OutputFormat::Png,
+    };
+    let output = output.unwrap_or_else(|| format!("result.{}", format.extension()));
+
     // Extract grid and crop parameters
     let (cols, rows) = grid;
 
     // Crop and combine images into a grid
-    let result_img = crop_and_grid_images(&loaded_images, crop, cols, rows);
+    let resize = resolve_resize_param(resize, scale, filter);
+    let result_img = run_with_thread_pool(threads, || crop_and_grid_images(&loaded_images, crop, cols, rows, resize));
 
-    // Save the output image file
-    if let Err(e) = result_img.save(&output) {
-        eprintln!("Failed to save output image: {}", e);
-        std::process::exit(1);
+    // Encode and save the output image file
+    let encoded = encode_image(&result_img, format).and_then(|bytes| {
+        if matches!(format, OutputFormat::Png) {
+            optimize_png(&bytes, optimize)
+        } else {
+            Ok(bytes)
+        }
+    });
+    match encoded {
+        Ok(bytes) => {
+            if let Err(e) = std::fs::write(&output, bytes) {
+                eprintln!("Failed to save output image: {}", e);
+                std::process::exit(1);
+            }
+        }
+        Err(e) => {
+            eprintln!("Failed to encode output image: {}", e);
+            std::process::exit(1);
+        }
     }
     println!("Saved output image to {}", output);
 }
@@ -151,8 +392,9 @@ fn run_crop_grid(images: Vec<String>, output: String, crop: (u32, u32, u32, u32)
 /// # Behavior
 /// - Loads each image from the given paths (exits on error)
 /// - Splits each image into tiles of the specified size and grid, starting from the offset
-/// - Saves all tiles as PNG files in a ZIP archive at the specified output path (exits on error)
-fn run_crop_split(images: Vec<String>, output: String, crop: (u32, u32, u32, u32), grid: (usize, usize), prefix: String) {
+/// - Saves all tiles, encoded with `format`, in a ZIP archive at the specified output path (exits on error)
+#[allow(clippy::too_many_arguments)]
+fn run_crop_split(images: Vec<String>, output: String, crop: (u32, u32, u32, u32), grid: (usize, usize), prefix: String, format: OutputFormat, threads: usize, optimize: u8, resize: Option<(u32, u32)>, scale: Option<f32>, filter: ResizeFilter, diff_threshold: Option<f32>, compression: ZipCompression) {
     // Sort image file paths
     let mut images = images;
     images.sort();
@@ -171,7 +413,10 @@ fn run_crop_split(images: Vec<String>, output: String, crop: (u32, u32, u32, u32
 
     // Crop and split images, then zip
     let (crop_w, crop_h, crop_x, crop_y) = crop;
-    match crop_split_image_to_zip(&loaded_images, (crop_w, crop_h), (crop_x, crop_y), grid, &prefix) {
+    let result = run_with_thread_pool(threads, || {
+        crop_split_image_to_zip(&loaded_images, (crop_w, crop_h), (crop_x, crop_y), grid, &prefix, format, optimize, resolve_resize_param(resize, scale, filter), diff_threshold, compression)
+    });
+    match result {
         Ok(zip_bytes) => {
             if let Err(e) = std::fs::write(&output, zip_bytes) {
                 eprintln!("Failed to write zip file '{}': {}", output, e);
@@ -191,11 +436,11 @@ fn main() {
     let args = Args::parse();
 
     match args.command {
-        Commands::CropGrid { images, output, crop, grid } => {
-            run_crop_grid(images, output, crop, grid);
+        Commands::CropGrid { images, output, crop, grid, format, threads, optimize, resize, scale, filter } => {
+            run_crop_grid(images, output, crop, grid, format, threads, optimize, resize, scale, filter);
         }
-        Commands::CropSplit { images, output, crop, grid, prefix } => {
-            run_crop_split(images, output, crop, grid, prefix);
+        Commands::CropSplit { images, output, crop, grid, prefix, format, threads, optimize, resize, scale, filter, diff_threshold, compression } => {
+            run_crop_split(images, output, crop, grid, prefix, format, threads, optimize, resize, scale, filter, diff_threshold, compression);
         }
     }
 }
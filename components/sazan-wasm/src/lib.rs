@@ -57,6 +57,7 @@ pub fn crop_and_grid_images(
         crop,
         grid_cols as usize,
         grid_rows as usize,
+        None,
     );
     // Convert output image to RGBA bytes
     let out_rgba = out_img.to_rgba8();
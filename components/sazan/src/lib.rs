@@ -1,66 +1,456 @@
 use std::io::Write;
 use image::{DynamicImage, GenericImageView, RgbaImage, GenericImage};
 
-/// Splits a single image into a grid of tiles and returns a ZIP archive (in memory) containing each tile as a PNG file.
+/// Output image encoder selected via the `--format` flag.
+///
+/// This is the single place that knows how to turn a `DynamicImage` into
+/// encoded bytes, so every subcommand that writes image files shares the
+/// same codec support and the same notion of "supported extensions".
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum OutputFormat {
+    Png,
+    Jpeg { quality: u8 },
+    WebP { quality: f32 },
+    Tiff,
+}
+
+impl OutputFormat {
+    /// File extensions (lowercase, no dot) accepted for this format.
+    pub fn extensions(&self) -> &'static [&'static str] {
+        match self {
+            OutputFormat::Png => &["png"],
+            OutputFormat::Jpeg { .. } => &["jpg", "jpeg"],
+            OutputFormat::WebP { .. } => &["webp"],
+            OutputFormat::Tiff => &["tif", "tiff"],
+        }
+    }
+
+    /// Canonical extension used when generating output filenames.
+    pub fn extension(&self) -> &'static str {
+        self.extensions()[0]
+    }
+
+    /// Looks up the format matching a file extension, using the format's
+    /// default quality settings. Returns `None` for unknown extensions.
+    pub fn from_extension(ext: &str) -> Option<OutputFormat> {
+        match ext.to_ascii_lowercase().as_str() {
+            "png" => Some(OutputFormat::Png),
+            "jpg" | "jpeg" => Some(OutputFormat::Jpeg { quality: 85 }),
+            "webp" => Some(OutputFormat::WebP { quality: 80.0 }),
+            "tif" | "tiff" => Some(OutputFormat::Tiff),
+            _ => None,
+        }
+    }
+}
+
+/// Extensions supported by the current build, in the order they should be
+/// presented to users (e.g. in `--help` text).
+pub fn supported_extensions() -> &'static [&'static str] {
+    &["png", "jpg", "jpeg", "webp", "tif", "tiff"]
+}
+
+/// Encodes `image` using the given output format.
 ///
 /// # Arguments
-/// * `image` - Source image to split
-/// * `crop_size` - (width, height) of each tile
-/// * `grid` - (columns, rows) grid size
-/// * `filename_prefix` - Prefix for each PNG file in the zip (e.g. "tile")
+/// * `image` - Image to encode
+/// * `format` - Target codec and quality settings
 ///
 /// # Returns
-/// * `Ok(Vec<u8>)` - ZIP archive as bytes (in memory)
-/// * `Err` - If any error occurs during processing
-/// Crops and splits multiple images into a grid of tiles each, starting from a given offset, and returns a ZIP archive (in memory) containing each tile as a PNG file.
+/// * `Ok(Vec<u8>)` - Encoded image bytes
+/// * `Err` - If the encoder rejects the image (e.g. unsupported color type)
+pub fn encode_image(
+    image: &DynamicImage,
+    format: OutputFormat,
+) -> Result<Vec<u8>, Box<dyn std::error::Error>> {
+    use image::ImageOutputFormat;
+    let mut bytes = std::io::Cursor::new(Vec::new());
+    match format {
+        OutputFormat::Png => image.write_to(&mut bytes, ImageOutputFormat::Png)?,
+        OutputFormat::Jpeg { quality } => image.write_to(&mut bytes, ImageOutputFormat::Jpeg(quality))?,
+        OutputFormat::Tiff => image.write_to(&mut bytes, ImageOutputFormat::Tiff)?,
+        OutputFormat::WebP { quality } => {
+            let encoder = webp::Encoder::from_image(image).map_err(|e| e.to_string())?;
+            bytes.write_all(&encoder.encode(quality))?;
+        }
+    }
+    Ok(bytes.into_inner())
+}
+
+/// Runs a lossless PNG optimization pass over already-encoded PNG bytes.
+///
+/// Re-deflates the IDAT stream at a higher compression effort, tries
+/// bit-depth/color-type reduction (e.g. RGBA->RGB when alpha is fully
+/// opaque, or RGB->palette when the image has few distinct colors), and
+/// drops non-essential ancillary chunks. Whichever of the original or
+/// optimized bytes is smaller is returned, so this is always safe to apply.
+///
+/// # Arguments
+/// * `png_bytes` - Already-encoded PNG bytes
+/// * `level` - Optimization level 0-6 (clamped); 0 skips the pass entirely
+///
+/// # Returns
+/// * `Ok(Vec<u8>)` - The smaller of the original or optimized PNG bytes
+/// * `Err` - If `png_bytes` is not a valid PNG
+pub fn optimize_png(png_bytes: &[u8], level: u8) -> Result<Vec<u8>, Box<dyn std::error::Error>> {
+    if level == 0 {
+        return Ok(png_bytes.to_vec());
+    }
+    let mut options = oxipng::Options::from_preset(level.min(6));
+    options.strip = oxipng::StripChunks::Safe;
+    let optimized = oxipng::optimize_from_memory(png_bytes, &options)?;
+    if optimized.len() < png_bytes.len() {
+        Ok(optimized)
+    } else {
+        Ok(png_bytes.to_vec())
+    }
+}
+
+/// One row of the `manifest.json` written alongside every split archive: records the source
+/// image, grid position, pixel rectangle a tile was cut from, and whether it was skipped for
+/// being unchanged from the previous image (see `diff_threshold`). Downstream tooling (texture
+/// packers, viewers) can reconstruct tile placement from this without parsing filenames.
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct TileManifestEntry {
+    pub filename: Option<String>,
+    pub image_index: usize,
+    pub row: usize,
+    pub col: usize,
+    pub x: u32,
+    pub y: u32,
+    pub width: u32,
+    pub height: u32,
+    pub skipped: bool,
+}
+
+/// ZIP compression method and level selected via the `--compression` flag.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ZipCompression {
+    Stored,
+    Deflate { level: Option<i32> },
+}
+
+impl ZipCompression {
+    fn file_options(&self) -> zip::write::FileOptions {
+        match self {
+            ZipCompression::Stored => {
+                zip::write::FileOptions::default().compression_method(zip::CompressionMethod::Stored)
+            }
+            ZipCompression::Deflate { level } => zip::write::FileOptions::default()
+                .compression_method(zip::CompressionMethod::Deflated)
+                .compression_level(*level),
+        }
+    }
+}
+
+/// Mean per-channel absolute difference between two images' RGBA bytes, scaled 0-255.
+/// Images of different sizes are compared over their overlapping byte range.
+fn mean_abs_diff(a: &DynamicImage, b: &DynamicImage) -> f32 {
+    let a = a.to_rgba8();
+    let b = b.to_rgba8();
+    let (a_bytes, b_bytes) = (a.as_raw(), b.as_raw());
+    let len = a_bytes.len().min(b_bytes.len());
+    if len == 0 {
+        return 0.0;
+    }
+    let sum: u64 = a_bytes
+        .iter()
+        .zip(b_bytes.iter())
+        .take(len)
+        .map(|(&x, &y)| (x as i32 - y as i32).unsigned_abs() as u64)
+        .sum();
+    sum as f32 / len as f32
+}
+
+/// Result of cropping and encoding a single tile: the manifest entry plus
+/// the encoded bytes, or `None` when the tile was skipped by `--diff-threshold`.
+type TileResult = Result<(TileManifestEntry, Option<Vec<u8>>), Box<dyn std::error::Error + Send + Sync>>;
+
+/// Crops and splits multiple images into a grid of tiles each, starting from a given offset, and returns a ZIP archive (in memory) containing each tile encoded with `format`.
 ///
 /// # Arguments
 /// * `images` - Source images to crop and split (each image will be processed independently)
 /// * `crop_size` - (width, height) of each tile
 /// * `offset` - (x, y) start position (top-left) for the grid cropping in each image
 /// * `grid` - (columns, rows) grid size
-/// * `filename_prefix` - Prefix for each PNG file in the zip (e.g. "tile")
+/// * `filename_prefix` - Prefix for each file in the zip (e.g. "tile")
+/// * `format` - Output codec used to encode every tile
+/// * `optimize_level` - Lossless PNG optimization level 0-6 (0 = skip); ignored for non-PNG formats
+/// * `resize` - optional (target size, filter) applied to each cropped tile before encoding
+/// * `diff_threshold` - If set, a tile is skipped (and omitted from the zip) when its mean
+///   per-channel absolute difference (scaled 0-255) from the same position in the previous
+///   image is at or below this value. The first image's tiles are always emitted.
+/// * `compression` - ZIP compression method (and level) applied to every entry
+///
+/// A `manifest.json` listing, for every tile, its filename (if emitted), source image index,
+/// grid position, pixel rectangle, and skip status is always added to the zip.
 ///
 /// # Returns
 /// * `Ok(Vec<u8>)` - ZIP archive as bytes (in memory)
 /// * `Err` - If any error occurs during processing
+#[allow(clippy::too_many_arguments)]
 pub fn crop_split_image_to_zip(
     images: &[DynamicImage],
     crop_size: (u32, u32),
     offset: (u32, u32),
     grid: (usize, usize),
     filename_prefix: &str,
-) -> Result<Vec<u8>, Box<dyn std::error::Error>> {
-    use zip::write::FileOptions;
+    format: OutputFormat,
+    optimize_level: u8,
+    resize: Option<(ResizeSpec, ResizeFilter)>,
+    diff_threshold: Option<f32>,
+    compression: ZipCompression,
+) -> Result<Vec<u8>, Box<dyn std::error::Error + Send + Sync>> {
+    use rayon::prelude::*;
     use zip::ZipWriter;
-    use image::ImageOutputFormat;
     let (tile_w, tile_h) = crop_size;
     let (offset_x, offset_y) = offset;
     let (cols, rows) = grid;
+    let ext = format.extension();
+    let tiles_per_image = rows * cols;
+    let resized_size = resize.map(|(spec, filter)| (spec.resolve(crop_size), filter));
+
+    // Crop and encode every tile in parallel, indexed by a flattened
+    // (img_idx, row, col) position so the work can be scheduled across
+    // cores without depending on write order.
+    let tiles: Vec<(TileManifestEntry, Option<Vec<u8>>)> = (0..images.len() * tiles_per_image)
+        .into_par_iter()
+        .map(|i| -> TileResult {
+            let img_idx = i / tiles_per_image;
+            let row = (i % tiles_per_image) / cols;
+            let col = (i % tiles_per_image) % cols;
+            let x = offset_x + col as u32 * tile_w;
+            let y = offset_y + row as u32 * tile_h;
+            let cropped = images[img_idx].crop_imm(x, y, tile_w, tile_h);
+
+            let skipped = match diff_threshold {
+                Some(threshold) if img_idx > 0 => {
+                    let previous = images[img_idx - 1].crop_imm(x, y, tile_w, tile_h);
+                    mean_abs_diff(&cropped, &previous) <= threshold
+                }
+                _ => false,
+            };
+            if skipped {
+                let entry = TileManifestEntry {
+                    filename: None, image_index: img_idx, row, col,
+                    x, y, width: tile_w, height: tile_h, skipped: true,
+                };
+                return Ok((entry, None));
+            }
+
+            let cropped = match resized_size {
+                Some((size, filter)) => resize_image(&cropped, size, filter),
+                None => cropped,
+            };
+            let encoded = encode_image(&cropped, format).map_err(|e| e.to_string())?;
+            let encoded = if matches!(format, OutputFormat::Png) {
+                optimize_png(&encoded, optimize_level).map_err(|e| e.to_string())?
+            } else {
+                encoded
+            };
+            let filename = format!("{}_{:02}_{:02}_{:02}.{}", filename_prefix, img_idx, row, col, ext);
+            let entry = TileManifestEntry {
+                filename: Some(filename), image_index: img_idx, row, col,
+                x, y, width: tile_w, height: tile_h, skipped: false,
+            };
+            Ok((entry, Some(encoded)))
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+
+    // ZipWriter isn't Send, so the archive itself is assembled sequentially
+    // from the already-encoded tiles.
     let mut buffer = std::io::Cursor::new(Vec::new());
     let mut zip = ZipWriter::new(&mut buffer);
-    let options = FileOptions::default().compression_method(zip::CompressionMethod::Stored);
-
-    for (img_idx, image) in images.iter().enumerate() {
-        for row in 0..rows {
-            for col in 0..cols {
-                let x = offset_x + col as u32 * tile_w;
-                let y = offset_y + row as u32 * tile_h;
-                let cropped = image.crop_imm(x, y, tile_w, tile_h);
-                let mut png_bytes = std::io::Cursor::new(Vec::new());
-                cropped.write_to(&mut png_bytes, ImageOutputFormat::Png)?;
-                let filename = format!("{}_{:02}_{:02}_{:02}.png", filename_prefix, img_idx, row, col);
-                zip.start_file(filename, options)?;
-                zip.write_all(&png_bytes.into_inner())?;
-            }
+    let options = compression.file_options();
+    let mut manifest = Vec::with_capacity(tiles.len());
+    for (entry, encoded) in tiles {
+        if let Some(encoded) = &encoded {
+            zip.start_file(entry.filename.clone().unwrap(), options)?;
+            zip.write_all(encoded)?;
         }
+        manifest.push(entry);
     }
+    zip.start_file("manifest.json", options)?;
+    zip.write_all(&serde_json::to_vec_pretty(&manifest)?)?;
     let _ = zip.finish()?;
     // Explicitly drop zip to release the borrow on buffer
     drop(zip);
     Ok(buffer.into_inner())
 }
 
+/// Resampling filter used by [`resize_images`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ResizeFilter {
+    Nearest,
+    Bilinear,
+    Lanczos3,
+}
+
+impl ResizeFilter {
+    /// Half-width (in source pixels, at scale 1:1) of the filter's support.
+    fn radius(&self) -> f32 {
+        match self {
+            ResizeFilter::Nearest => 0.5,
+            ResizeFilter::Bilinear => 1.0,
+            ResizeFilter::Lanczos3 => 3.0,
+        }
+    }
+
+    /// Filter weight at distance `x` (in source pixels), for a support already scaled for up/downsampling.
+    fn weight(&self, x: f32) -> f32 {
+        match self {
+            ResizeFilter::Nearest => {
+                if x.abs() < 0.5 {
+                    1.0
+                } else {
+                    0.0
+                }
+            }
+            ResizeFilter::Bilinear => (1.0 - x.abs()).max(0.0),
+            ResizeFilter::Lanczos3 => {
+                let x = x.abs();
+                if x < 1e-6 {
+                    1.0
+                } else if x >= 3.0 {
+                    0.0
+                } else {
+                    let px = std::f32::consts::PI * x;
+                    let px3 = px / 3.0;
+                    (px.sin() / px) * (px3.sin() / px3)
+                }
+            }
+        }
+    }
+}
+
+/// Target dimensions for [`resize_images`], either an absolute size or a uniform scale factor.
+#[derive(Debug, Clone, Copy)]
+pub enum ResizeSpec {
+    Absolute(u32, u32),
+    Scale(f32),
+}
+
+impl ResizeSpec {
+    /// Resolves this spec against a source size, producing concrete output dimensions (at least 1x1).
+    pub fn resolve(&self, src: (u32, u32)) -> (u32, u32) {
+        match *self {
+            ResizeSpec::Absolute(w, h) => (w.max(1), h.max(1)),
+            ResizeSpec::Scale(factor) => (
+                ((src.0 as f32 * factor).round() as u32).max(1),
+                ((src.1 as f32 * factor).round() as u32).max(1),
+            ),
+        }
+    }
+}
+
+/// Per-output-pixel source range and normalized weights for one axis of a separable resample.
+struct AxisWeights {
+    start: u32,
+    weights: Vec<f32>,
+}
+
+/// Precomputes, for every output pixel along one axis, the range of source pixels that
+/// contribute to it and their normalized weights. Downscaling widens the filter support
+/// (divides distances by the scale factor) so the result is anti-aliased instead of aliased.
+fn precompute_axis_weights(src_len: u32, dst_len: u32, filter: ResizeFilter) -> Vec<AxisWeights> {
+    let scale = src_len as f32 / dst_len as f32;
+    let filter_scale = scale.max(1.0);
+    let radius = filter.radius() * filter_scale;
+    (0..dst_len)
+        .map(|i| {
+            let center = (i as f32 + 0.5) * scale - 0.5;
+            let start = ((center - radius).floor().max(0.0) as u32).min(src_len - 1);
+            let end = ((center + radius).ceil() as i64).clamp(start as i64, src_len as i64 - 1) as u32;
+            let mut weights: Vec<f32> = (start..=end)
+                .map(|j| filter.weight((j as f32 - center) / filter_scale))
+                .collect();
+            let sum: f32 = weights.iter().sum();
+            if sum.abs() > 1e-6 {
+                weights.iter_mut().for_each(|w| *w /= sum);
+            }
+            AxisWeights { start, weights }
+        })
+        .collect()
+}
+
+/// Resizes each image to `size` using a fast separable-kernel resampler: a horizontal pass
+/// produces an intermediate buffer, then a vertical pass produces the final image. Each
+/// output pixel's source range and weights are precomputed once per axis and reused across
+/// every row/column, avoiding per-pixel recomputation.
+///
+/// # Arguments
+/// * `images` - Images to resize
+/// * `size` - Target (width, height)
+/// * `filter` - Resampling filter to use
+///
+/// # Returns
+/// * `Vec<DynamicImage>` - Resized images, in the same order as `images`
+pub fn resize_images(images: &[DynamicImage], size: (u32, u32), filter: ResizeFilter) -> Vec<DynamicImage> {
+    use rayon::prelude::*;
+    images.par_iter().map(|img| resize_image(img, size, filter)).collect()
+}
+
+fn resize_image(image: &DynamicImage, size: (u32, u32), filter: ResizeFilter) -> DynamicImage {
+    let (dst_w, dst_h) = size;
+    let src = image.to_rgba8();
+    let (src_w, src_h) = src.dimensions();
+    if (src_w, src_h) == (dst_w, dst_h) {
+        return DynamicImage::ImageRgba8(src);
+    }
+
+    // Horizontal pass: src_w x src_h -> dst_w x src_h
+    let horizontal_weights = precompute_axis_weights(src_w, dst_w, filter);
+    let mut intermediate = RgbaImage::new(dst_w, src_h);
+    for y in 0..src_h {
+        for (x, w) in horizontal_weights.iter().enumerate() {
+            intermediate.put_pixel(x as u32, y, to_pixel(sample_row(&src, w, y)));
+        }
+    }
+
+    // Vertical pass: dst_w x src_h -> dst_w x dst_h
+    let vertical_weights = precompute_axis_weights(src_h, dst_h, filter);
+    let mut result = RgbaImage::new(dst_w, dst_h);
+    for (y, w) in vertical_weights.iter().enumerate() {
+        for x in 0..dst_w {
+            result.put_pixel(x, y as u32, to_pixel(sample_column(&intermediate, w, x)));
+        }
+    }
+    DynamicImage::ImageRgba8(result)
+}
+
+/// Weighted sum of source pixels `[w.start, w.start + w.weights.len())` on row `y`.
+fn sample_row(src: &RgbaImage, w: &AxisWeights, y: u32) -> [f32; 4] {
+    let mut acc = [0f32; 4];
+    for (k, &wt) in w.weights.iter().enumerate() {
+        let px = src.get_pixel(w.start + k as u32, y).0;
+        for c in 0..4 {
+            acc[c] += px[c] as f32 * wt;
+        }
+    }
+    acc
+}
+
+/// Weighted sum of source pixels `[w.start, w.start + w.weights.len())` on column `x`.
+fn sample_column(src: &RgbaImage, w: &AxisWeights, x: u32) -> [f32; 4] {
+    let mut acc = [0f32; 4];
+    for (k, &wt) in w.weights.iter().enumerate() {
+        let px = src.get_pixel(x, w.start + k as u32).0;
+        for c in 0..4 {
+            acc[c] += px[c] as f32 * wt;
+        }
+    }
+    acc
+}
+
+/// Rounds and clamps a float RGBA accumulator into a pixel.
+fn to_pixel(acc: [f32; 4]) -> image::Rgba<u8> {
+    image::Rgba([
+        acc[0].round().clamp(0.0, 255.0) as u8,
+        acc[1].round().clamp(0.0, 255.0) as u8,
+        acc[2].round().clamp(0.0, 255.0) as u8,
+        acc[3].round().clamp(0.0, 255.0) as u8,
+    ])
+}
+
 /// Converts raw RGBA bytes (from browser ImageData) to a DynamicImage.
 ///
 /// # Arguments
@@ -76,13 +466,14 @@ pub fn dynamic_image_from_rgba_bytes(rgba: &[u8], width: u32, height: u32) -> Dy
     DynamicImage::ImageRgba8(img)
 }
 
-/// Load images, crop, and combine into a grid
+/// Crop images, optionally resize, and combine into a grid
 ///
 /// # Arguments
 /// * `paths` - image file paths
 /// * `crop` - (width, height, x, y)
 /// * `cols` - grid columns
 /// * `rows` - grid rows
+/// * `resize` - optional (target size, filter) applied to each cropped region before gridding
 ///
 /// # Returns
 /// * Ok(DynamicImage) - combined image
@@ -92,8 +483,16 @@ pub fn crop_and_grid_images(
     crop: (u32, u32, u32, u32),
     cols: usize,
     rows: usize,
+    resize: Option<(ResizeSpec, ResizeFilter)>,
 ) -> DynamicImage {
     let cropped_images = crop_images(images, crop);
+    let cropped_images = match resize {
+        Some((spec, filter)) => {
+            let size = spec.resolve((crop.0, crop.1));
+            resize_images(&cropped_images, size, filter)
+        }
+        None => cropped_images,
+    };
     combine_grid(cropped_images, cols, rows)
 }
 
@@ -111,8 +510,9 @@ fn crop_images(
     images: &[DynamicImage],
     crop: (u32, u32, u32, u32),
 ) -> Vec<DynamicImage> {
+    use rayon::prelude::*;
     images
-        .iter()
+        .par_iter()
         .map(|img| img.crop_imm(crop.2, crop.3, crop.0, crop.1))
         .collect()
 }
@@ -157,3 +557,182 @@ fn combine_grid(
     }
     DynamicImage::ImageRgba8(canvas)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn solid_image(w: u32, h: u32, color: [u8; 4]) -> DynamicImage {
+        DynamicImage::ImageRgba8(RgbaImage::from_pixel(w, h, image::Rgba(color)))
+    }
+
+    /// CRC-32 (IEEE 802.3 polynomial), used to build a well-formed PNG ancillary
+    /// chunk for `optimize_png_strips_ancillary_chunks` below.
+    fn crc32(bytes: &[u8]) -> u32 {
+        let mut crc: u32 = 0xFFFFFFFF;
+        for &b in bytes {
+            crc ^= b as u32;
+            for _ in 0..8 {
+                crc = if crc & 1 != 0 { (crc >> 1) ^ 0xEDB88320 } else { crc >> 1 };
+            }
+        }
+        !crc
+    }
+
+    /// Inserts a `tEXt` ancillary chunk into an encoded PNG, just before `IEND`.
+    fn insert_text_chunk(png: &[u8], keyword: &[u8], text: &[u8]) -> Vec<u8> {
+        let mut data = keyword.to_vec();
+        data.push(0);
+        data.extend_from_slice(text);
+
+        let mut chunk = Vec::new();
+        chunk.extend_from_slice(&(data.len() as u32).to_be_bytes());
+        chunk.extend_from_slice(b"tEXt");
+        chunk.extend_from_slice(&data);
+        let crc_input = [b"tEXt".as_slice(), &data].concat();
+        chunk.extend_from_slice(&crc32(&crc_input).to_be_bytes());
+
+        let insert_at = png.len() - 12; // length+type+crc of the trailing empty IEND chunk
+        let mut out = png[..insert_at].to_vec();
+        out.extend_from_slice(&chunk);
+        out.extend_from_slice(&png[insert_at..]);
+        out
+    }
+
+    /// Walks a PNG's chunk stream looking for `chunk_type` (e.g. `b"tEXt"`).
+    fn contains_chunk(png: &[u8], chunk_type: &[u8; 4]) -> bool {
+        let mut pos = 8; // skip the 8-byte signature
+        while pos + 8 <= png.len() {
+            let len = u32::from_be_bytes(png[pos..pos + 4].try_into().unwrap()) as usize;
+            if &png[pos + 4..pos + 8] == chunk_type {
+                return true;
+            }
+            pos += 8 + len + 4; // length + type + data + crc
+        }
+        false
+    }
+
+    #[test]
+    fn crop_split_skips_tiles_unchanged_from_the_previous_image() {
+        let first = solid_image(4, 4, [0, 0, 0, 255]);
+        let second = solid_image(4, 4, [0, 0, 0, 255]); // identical to `first`
+        let zip_bytes = crop_split_image_to_zip(
+            &[first, second],
+            (2, 2),
+            (0, 0),
+            (2, 2),
+            "tile",
+            OutputFormat::Png,
+            0,
+            None,
+            Some(0.0),
+            ZipCompression::Stored,
+        )
+        .unwrap();
+
+        let archive = zip::ZipArchive::new(std::io::Cursor::new(zip_bytes)).unwrap();
+        let names: Vec<&str> = archive.file_names().collect();
+
+        // The first image's tiles are always emitted, regardless of diff_threshold.
+        for (row, col) in [(0, 0), (0, 1), (1, 0), (1, 1)] {
+            assert!(names.contains(&format!("tile_00_{:02}_{:02}.png", row, col).as_str()));
+        }
+        // The second image's tiles are identical to the first's, so none are emitted.
+        for (row, col) in [(0, 0), (0, 1), (1, 0), (1, 1)] {
+            assert!(!names.contains(&format!("tile_01_{:02}_{:02}.png", row, col).as_str()));
+        }
+    }
+
+    #[test]
+    fn crop_split_manifest_records_tile_rectangles_and_positions() {
+        let img = solid_image(4, 4, [0, 0, 0, 255]);
+        let zip_bytes = crop_split_image_to_zip(
+            &[img],
+            (2, 2),
+            (1, 1),
+            (2, 2),
+            "tile",
+            OutputFormat::Png,
+            0,
+            None,
+            None,
+            ZipCompression::Stored,
+        )
+        .unwrap();
+
+        let mut archive = zip::ZipArchive::new(std::io::Cursor::new(zip_bytes)).unwrap();
+        let mut manifest_json = String::new();
+        use std::io::Read;
+        archive.by_name("manifest.json").unwrap().read_to_string(&mut manifest_json).unwrap();
+        let manifest: Vec<TileManifestEntry> = serde_json::from_str(&manifest_json).unwrap();
+
+        assert_eq!(manifest.len(), 4);
+        for (row, col) in [(0, 0), (0, 1), (1, 0), (1, 1)] {
+            let entry = manifest.iter().find(|e| e.row == row && e.col == col).unwrap();
+            assert_eq!(entry.image_index, 0);
+            assert_eq!((entry.x, entry.y), (1 + col as u32 * 2, 1 + row as u32 * 2));
+            assert_eq!((entry.width, entry.height), (2, 2));
+            assert!(!entry.skipped);
+            assert_eq!(entry.filename.as_deref(), Some(format!("tile_00_{:02}_{:02}.png", row, col).as_str()));
+        }
+    }
+
+    #[test]
+    fn crop_split_deflate_archive_tiles_decode() {
+        let img = solid_image(4, 4, [12, 34, 56, 255]);
+        let zip_bytes = crop_split_image_to_zip(
+            &[img],
+            (2, 2),
+            (0, 0),
+            (2, 2),
+            "tile",
+            OutputFormat::Png,
+            0,
+            None,
+            None,
+            ZipCompression::Deflate { level: None },
+        )
+        .unwrap();
+
+        let mut archive = zip::ZipArchive::new(std::io::Cursor::new(zip_bytes)).unwrap();
+        let mut tile_bytes = Vec::new();
+        use std::io::Read;
+        archive.by_name("tile_00_00_00.png").unwrap().read_to_end(&mut tile_bytes).unwrap();
+
+        let decoded = image::load_from_memory(&tile_bytes).unwrap();
+        assert_eq!(decoded.dimensions(), (2, 2));
+        assert_eq!(decoded.to_rgba8().get_pixel(0, 0).0, [12, 34, 56, 255]);
+    }
+
+    #[test]
+    fn resize_images_produces_requested_dimensions() {
+        let img = solid_image(4, 4, [255, 0, 0, 255]);
+        let resized = resize_images(&[img], (2, 6), ResizeFilter::Lanczos3);
+        assert_eq!(resized[0].dimensions(), (2, 6));
+    }
+
+    #[test]
+    fn resize_images_nearest_preserves_solid_color() {
+        let img = solid_image(8, 8, [10, 20, 30, 255]);
+        let resized = resize_images(&[img], (3, 5), ResizeFilter::Nearest);
+        assert_eq!(resized[0].to_rgba8().get_pixel(1, 2).0, [10, 20, 30, 255]);
+    }
+
+    #[test]
+    fn optimize_png_level_zero_is_passthrough() {
+        let img = solid_image(4, 4, [1, 2, 3, 255]);
+        let png = encode_image(&img, OutputFormat::Png).unwrap();
+        assert_eq!(optimize_png(&png, 0).unwrap(), png);
+    }
+
+    #[test]
+    fn optimize_png_strips_ancillary_chunks() {
+        let img = solid_image(4, 4, [1, 2, 3, 255]);
+        let png = encode_image(&img, OutputFormat::Png).unwrap();
+        let with_text_chunk = insert_text_chunk(&png, b"Comment", b"hello");
+        assert!(contains_chunk(&with_text_chunk, b"tEXt"));
+
+        let optimized = optimize_png(&with_text_chunk, 6).unwrap();
+        assert!(!contains_chunk(&optimized, b"tEXt"));
+    }
+}